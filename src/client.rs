@@ -0,0 +1,141 @@
+use std::io::{self, Read, Write};
+
+use crate::codec::Codec;
+use crate::commands::{CommandError, PowerState};
+
+/// Typed controller for the other end of the ESC/VP21 link.
+///
+/// Where the emulator answers the wire protocol, `EpsonClient` drives it:
+/// each method writes `"{cmd}\r"`, waits for the `\r:` prompt, and parses the
+/// `CMD=value`/`ERR` reply into a Rust value. The same client talks to the
+/// emulator or to real hardware over any serial/TCP `T: Read + Write`.
+pub struct EpsonClient<T: Read + Write> {
+    port: T,
+    codec: Codec,
+    buf: Vec<u8>,
+}
+
+impl<T: Read + Write> EpsonClient<T> {
+    pub fn new(port: T) -> EpsonClient<T> {
+        EpsonClient {
+            port,
+            codec: Codec::new(),
+            buf: vec![0; 128],
+        }
+    }
+
+    /// Powers the lamp on; returns once the command is acknowledged.
+    pub fn power_on(&mut self) -> Result<(), CommandError> {
+        self.set("PWR", "ON")
+    }
+
+    /// Powers the lamp off.
+    pub fn power_off(&mut self) -> Result<(), CommandError> {
+        self.set("PWR", "OFF")
+    }
+
+    /// Current power state, defaulting to [`PowerState::PowerOff`] when the
+    /// projector is unreachable or returns an unknown code.
+    pub fn power_state(&mut self) -> PowerState {
+        self.query("PWR?")
+            .ok()
+            .and_then(|code| PowerState::from_code(&code))
+            .unwrap_or(PowerState::PowerOff)
+    }
+
+    /// Accumulated lamp hours, or `0` if the query fails.
+    pub fn lamp_hours(&mut self) -> u32 {
+        self.query("LAMP?")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Selects the active input source (two-character source code).
+    pub fn set_source(&mut self, source: &str) -> Result<(), CommandError> {
+        self.set("SOURCE", source)
+    }
+
+    /// Sends a raw query (e.g. `"SNO?"`) and returns the parsed value.
+    ///
+    /// `CMD=value` yields `value`; a bare `ERR` reply maps to
+    /// [`CommandError::InvalidCommand`], and a transport failure to
+    /// [`CommandError::InvalidQuery`].
+    pub fn query(&mut self, cmd: &str) -> Result<String, CommandError> {
+        let reply = self.transact(cmd).map_err(|_| CommandError::InvalidQuery)?;
+        parse_reply(&reply)
+    }
+
+    /// Issues a `CMD value` set and checks it was not rejected.
+    fn set(&mut self, cmd: &str, value: &str) -> Result<(), CommandError> {
+        let reply = self
+            .transact(&format!("{cmd} {value}"))
+            .map_err(|_| CommandError::InvalidQuery)?;
+        if reply == "ERR" {
+            Err(CommandError::InvalidCommand)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes `"{cmd}\r"` and reads back a single `\r`-terminated reply.
+    fn transact(&mut self, cmd: &str) -> io::Result<String> {
+        self.port.write_all(format!("{cmd}\r").as_bytes())?;
+        loop {
+            let n = self.port.read(&mut self.buf)?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+            }
+            if let Some(line) = self.codec.decode(&self.buf[..n])? {
+                return Ok(line);
+            }
+        }
+    }
+}
+
+/// Splits a `CMD=value` reply into its value, turning `ERR` into an error.
+fn parse_reply(reply: &str) -> Result<String, CommandError> {
+    if reply == "ERR" {
+        return Err(CommandError::InvalidCommand);
+    }
+    match reply.split_once('=') {
+        Some((_, value)) => Ok(value.to_string()),
+        None => Err(CommandError::InvalidQuery),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serialport::TTYPort;
+
+    use crate::escvp21::{start, SessionConfig};
+
+    /// Spawns the emulator on one end of a TTY pair and returns a client bound
+    /// to the other. `warming`/`cooling` of zero make power transitions settle
+    /// on the next query.
+    fn emulated(warming: u32, cooling: u32) -> EpsonClient<TTYPort> {
+        let (master, mut slave) = TTYPort::pair().unwrap();
+        std::thread::spawn(move || {
+            start(&mut slave, warming, cooling, SessionConfig::default());
+        });
+        EpsonClient::new(master)
+    }
+
+    #[test]
+    fn test_power_roundtrip() {
+        let mut client = emulated(0, 0);
+        client.power_on().unwrap();
+        // Warming is zero, so the next state read finds the lamp on.
+        assert!(matches!(client.power_state(), PowerState::LampOn));
+    }
+
+    #[test]
+    fn test_query_and_error() {
+        let mut client = emulated(0, 0);
+        // A value query round-trips through the `\r:` prompt.
+        assert_eq!(client.query("SNO?").unwrap(), "1234567890");
+        // A power-dependent query while off comes back as `ERR`.
+        assert_eq!(client.query("LAMP?"), Err(CommandError::InvalidCommand));
+    }
+}
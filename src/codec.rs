@@ -6,6 +6,12 @@ pub struct Codec {
     buffer: BytesMut,
 }
 
+impl Default for Codec {
+    fn default() -> Codec {
+        Codec::new()
+    }
+}
+
 impl Codec {
     pub fn new() -> Codec {
         Codec {
@@ -22,7 +28,7 @@ impl Codec {
             line.resize(line.len() - 1, 0); // Removing the training \r
             let str_result = match std::str::from_utf8(line.as_ref()) {
                 Ok(s) => Ok(Some(s.to_string())),
-                Err(_) => Err(io::Error::new(io::ErrorKind::Other, "Invalid String")),
+                Err(_) => Err(io::Error::other("Invalid String")),
             };
             self.buffer.clear();
             return str_result;
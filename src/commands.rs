@@ -20,33 +20,60 @@ pub enum CommandError {
     InvalidPowerState,
 }
 
-pub struct Param<'a> {
-    default: &'a str,
+/// Errors surfaced while loading an external projector model definition.
+#[derive(Error, Debug)]
+pub enum ModelError {
+    #[error("Cannot read model file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Cannot parse model file: {0}")]
+    Parse(String),
+    #[error("Invalid validation regex for command {command}: {source}")]
+    Regex {
+        command: String,
+        source: regex::Error,
+    },
+}
+
+pub struct Param {
+    default: String,
     value: Option<String>,
     validation: Option<Regex>,
     supported_in_power_off: bool,
 }
 
-impl<'a> Param<'a> {
-    fn new(default: &'a str, validation: &str, supported_in_power_off: bool) -> Param<'a> {
-        let validation = if validation.len() > 0 {
-            Some(Regex::new(validation).unwrap())
+impl Param {
+    fn new(command: &str, default: &str, validation: &str, supported_in_power_off: bool) -> Param {
+        // The built-in table only feeds known-good regexes, so the file-load
+        // path ([`Param::try_new`]) carries the fallible version.
+        Param::try_new(command, default, validation, supported_in_power_off)
+            .expect("built-in command definition is invalid")
+    }
+
+    /// Builds a [`Param`] from model-definition fields, compiling `validation`
+    /// and reporting a [`ModelError`] naming `command` instead of panicking on
+    /// a bad regex.
+    pub fn try_new(command: &str, default: &str, validation: &str, supported_in_power_off: bool) -> Result<Param, ModelError> {
+        let validation = if !validation.is_empty() {
+            Some(Regex::new(validation).map_err(|source| ModelError::Regex {
+                command: command.to_string(),
+                source,
+            })?)
         } else {
             None
         };
 
-        let value = if default.len() > 0 {
+        let value = if !default.is_empty() {
             Some(default.to_string())
         } else {
             None
         };
 
-        Param {
-            default,
+        Ok(Param {
+            default: default.to_string(),
             value,
             validation,
             supported_in_power_off
-        }
+        })
     }
 
     pub fn get_value(&self) -> Result<String, CommandError> {
@@ -125,6 +152,20 @@ impl PowerState {
             PowerState::LampOn => "01",
         }
     }
+
+    /// Reconstructs a state from a `PWR?` status code as seen on the wire.
+    ///
+    /// The transitional states restart their timer from now: a client reading
+    /// the code cannot know how long warming/cooling has already run.
+    pub fn from_code(code: &str) -> Option<PowerState> {
+        match code {
+            "00" => Some(PowerState::PowerOff),
+            "01" => Some(PowerState::LampOn),
+            "02" => Some(PowerState::Warming(SystemTime::now())),
+            "03" => Some(PowerState::Cooling(SystemTime::now())),
+            _ => None,
+        }
+    }
 }
 
 
@@ -135,47 +176,91 @@ const ON_OFF: &str = "(OFF|ON)";
 const LAMP_HOURS_DEFAULT: &str = "100";
 const AUTOHOME_DEFAULT: &str = "00";
 
-pub struct CommandProcessor<'a> {
-    commands: HashMap<&'static str, Param<'a>>,
+/// Fault code reported by `ERR?` when the projector is healthy.
+const NO_FAULT: &str = "00";
+/// `PWR?` status returned while a fault is active (standby, abnormal).
+const PWR_ABNORMAL: &str = "04";
+
+/// The command table shipped when no external model file is supplied.
+pub fn default_commands() -> HashMap<String, Param> {
+    [
+        ("SNO",Param::new("SNO","1234567890","", true)),
+        //("PWR", Param::new("PWR", "00", ON_OFF)),
+        ("LAMP",Param::new("LAMP",LAMP_HOURS_DEFAULT,"", false)),
+        ("KEY", Param::new("KEY","", "[A-Z0-9]{2}|INIT", false)),
+        ("FREEZE", Param::new("FREEZE","OFF", ON_OFF, false)),
+        ("FASTBOOT", Param::new("FASTBOOT","01", TWO_DIGITS, false)),
+        ("AUTOHOME",Param::new("AUTOHOME",AUTOHOME_DEFAULT,TWO_CHARS, false)),
+        ("SIGNAL",Param::new("SIGNAL","01","", false)),
+        ("ONTIME",Param::new("ONTIME","110","", false)),
+        // ERR is owned by the fault subsystem (see CommandProcessor::fault).
+        ("SOURCE",Param::new("SOURCE","00",TWO_CHARS, false)),
+        ("MUTE",Param::new("MUTE","0000",ON_OFF, false)),
+        ("VOL",Param::new("VOL","90","\\d+", false)),
+        ("ZOOM",Param::new("ZOOM","0","\\d{1,3}", false)),
+        ("HREVERSE", Param::new("HREVERSE","ON",ON_OFF, false)),
+        ("VREVERSE", Param::new("VREVERSE","ON", ON_OFF, false)),
+        ("IMGSHIFT", Param::new("IMGSHIFT","0 1", "-?[0-2] -?[0-2]", false)),
+        ("REFRESHTIME", Param::new("REFRESHTIME","00", TWO_DIGITS, false))
+    ]
+    .into_iter()
+    .map(|(name, param)| (name.to_string(), param))
+    .collect()
+}
+
+pub struct CommandProcessor {
+    commands: HashMap<String, Param>,
     power_state: PowerState,
     warming: Duration,
-    cooling: Duration
+    cooling: Duration,
+    fault: String,
 }
 
-impl<'a> CommandProcessor<'a> {
-    pub fn new(warming: u64, cooling: u64) -> CommandProcessor<'a> {
-        let mut processor = CommandProcessor {
-            commands: HashMap::new(),
+impl CommandProcessor {
+    pub fn new(warming: u64, cooling: u64) -> CommandProcessor {
+        CommandProcessor::with_commands(default_commands(), warming, cooling)
+    }
+
+    /// Builds a processor whose command table is loaded from an external
+    /// model definition (see [`crate::model`]). A missing, unparsable or
+    /// invalid file returns a [`ModelError`] rather than falling back to the
+    /// built-in table; bad regexes surface as [`ModelError::Regex`] instead of
+    /// panicking.
+    pub fn from_model(path: &std::path::Path, warming: u64, cooling: u64) -> Result<CommandProcessor, ModelError> {
+        let commands = crate::model::load(path)?;
+        Ok(CommandProcessor::with_commands(commands, warming, cooling))
+    }
+
+    fn with_commands(commands: HashMap<String, Param>, warming: u64, cooling: u64) -> CommandProcessor {
+        CommandProcessor {
+            commands,
             power_state: PowerState::PowerOff,
             warming: Duration::from_secs(warming),
-            cooling: Duration::from_secs(cooling)
-        };
+            cooling: Duration::from_secs(cooling),
+            fault: NO_FAULT.to_string(),
+        }
+    }
+
+    /// Injects a fault code so the emulator can simulate thermal shutdowns,
+    /// lamp faults and the like. A two-digit code is required; `"00"` clears
+    /// the fault and restores normal behaviour.
+    pub fn set_fault(&mut self, code: &str) -> Result<(), CommandError> {
+        if code.len() == 2 && code.bytes().all(|b| b.is_ascii_digit()) {
+            self.fault = code.to_string();
+            Ok(())
+        } else {
+            Err(CommandError::InvalidValue)
+        }
+    }
+
+    /// The currently active fault code (`"00"` when healthy).
+    pub fn fault(&self) -> &str {
+        &self.fault
+    }
 
-        let actual_commands = HashMap::from([
-                ("SNO",Param::new("1234567890","", true)),
-                //("PWR", Param::new("00", ON_OFF)),
-                ("LAMP",Param::new(LAMP_HOURS_DEFAULT,"", false)),
-                ("KEY", Param::new("", "[A-Z0-9]{2}|INIT", false)),
-                ("FREEZE", Param::new("OFF", ON_OFF, false)),
-                ("FASTBOOT", Param::new("01", TWO_DIGITS, false)),
-                ("AUTOHOME",Param::new(AUTOHOME_DEFAULT,TWO_CHARS, false)),
-                ("SIGNAL",Param::new("01","", false)),
-                ("ONTIME",Param::new("110","", false)),
-                ("LAMP",Param::new("100","", false)),
-                ("ERR",Param::new("00","", true)),
-                ("SOURCE",Param::new("00",TWO_CHARS, false)),
-                ("MUTE",Param::new("0000",ON_OFF, false)),
-                ("VOL",Param::new("90","\\d+", false)),
-                ("AUTOHOME",Param::new("00",TWO_CHARS, false)),
-                ("ZOOM",Param::new("0","\\d{1,3}", false)),
-                ("HREVERSE", Param::new("ON",ON_OFF, false)),
-                ("VREVERSE", Param::new("ON", ON_OFF, false)),
-                ("IMGSHIFT", Param::new("0 1", "-?[0-2] -?[0-2]", false)),
-                ("REFRESHTIME", Param::new("00", TWO_DIGITS, false))
-            ]);
-
-        processor.commands = actual_commands;
-        processor
+    #[inline]
+    fn fault_active(&self) -> bool {
+        self.fault != NO_FAULT
     }
 
     fn process_power_set(&mut self, value: &str) -> Result<(), CommandError> {
@@ -220,14 +305,26 @@ impl<'a> CommandProcessor<'a> {
     }
 
     fn process_query(&mut self, command: &str) -> Result<String, CommandError> {
-        let value = if command == "PWR" {
-            Ok(self.process_power_query().to_string())
+        let value = if command == "ERR" {
+            Ok(self.fault.clone())
+        } else if command == "PWR" {
+            if self.fault_active() {
+                Ok(PWR_ABNORMAL.to_string())
+            } else {
+                Ok(self.process_power_query().to_string())
+            }
         } else {
             let power_state = self.get_power_state();
             if let Some(param) = self.commands.get(command) {
-                match (param.supported_in_power_off(), power_state) {
-                    (true, _) | (false, PowerState::LampOn) => param.get_value(),
-                    _ => Err(CommandError::InvalidPowerState),
+                // An active fault rejects power-dependent queries just like a
+                // lamp that never warmed up.
+                if self.fault_active() && !param.supported_in_power_off() {
+                    Err(CommandError::InvalidPowerState)
+                } else {
+                    match (param.supported_in_power_off(), power_state) {
+                        (true, _) | (false, PowerState::LampOn) => param.get_value(),
+                        _ => Err(CommandError::InvalidPowerState),
+                    }
                 }
             } else {
                 Err(CommandError::InvalidCommand)
@@ -239,13 +336,15 @@ impl<'a> CommandProcessor<'a> {
     fn process_set(&mut self, command: &str, value: &str) -> Result<(), CommandError> {
         if command == "PWR" {
             self.process_power_set(value)
+        } else if command == "ERR" {
+            self.set_fault(value)
         } else {
             let power_state = self.get_power_state();
 
             if let Some(param) = self.commands.get_mut(command) {
                 match (param.supported_in_power_off(), power_state) {
                     (true, _) | (false, PowerState::LampOn) => param.set_value(value),
-                    _ => return Err(CommandError::InvalidPowerState),
+                    _ => Err(CommandError::InvalidPowerState),
                 }
             } else {
                 Err(CommandError::InvalidCommand)
@@ -258,14 +357,17 @@ impl<'a> CommandProcessor<'a> {
             let result = self.process_query(&message[0..message.len()-1])?;
             Ok(Some(result))
         } else {
-            let result = Regex::new("([A-Z][A-Z0-9]+) (.+)").unwrap().captures(message).map(|cap| {
-                let command = cap.get(1).ok_or(CommandError::InvalidCommand)?;
-                let value = cap.get(2).ok_or(CommandError::InvalidValue)?;
-
-                self.process_set(command.as_str(), value.as_str())?;
-                Ok(None)
-            }).unwrap();
-            result
+            // A line that is neither a query nor a `CMD value` set (e.g. a bare
+            // `\r` or a tokenless command) is rejected rather than panicking;
+            // the network loop feeds arbitrary bytes through here.
+            let cap = Regex::new("([A-Z][A-Z0-9]+) (.+)").unwrap()
+                .captures(message)
+                .ok_or(CommandError::InvalidCommand)?;
+            let command = cap.get(1).ok_or(CommandError::InvalidCommand)?;
+            let value = cap.get(2).ok_or(CommandError::InvalidValue)?;
+
+            self.process_set(command.as_str(), value.as_str())?;
+            Ok(None)
         }
     }
 }
@@ -293,7 +395,7 @@ mod tests {
     #[test]
     fn test_power_state_logic() {
         let mut processor = CommandProcessor::new(WARMING_TIME, COOLDOWN_TIME);
-        assert_eq!(processor.process_message("SNO?").unwrap().is_some(), true);
+        assert!(processor.process_message("SNO?").unwrap().is_some());
         assert_eq!(processor.process_message("LAMP?"), Err(CommandError::InvalidPowerState));
         assert_eq!(processor.process_message("PWR ON").unwrap(), None);
         assert_eq!(processor.process_message("LAMP?"), Err(CommandError::InvalidPowerState));
@@ -304,7 +406,7 @@ mod tests {
     #[test]
     fn test_set_get() {
         let mut processor = CommandProcessor::new(WARMING_TIME, COOLDOWN_TIME);
-        assert_eq!(processor.process_message("SNO?").unwrap().is_some(), true);
+        assert!(processor.process_message("SNO?").unwrap().is_some());
         assert_eq!(processor.process_message("SNO 1234567890"), Err(CommandError::InvalidCommand));
         assert_eq!(processor.process_message("PWR ON").unwrap(), None);
         std::thread::sleep(Duration::from_secs(WARMING_TIME));
@@ -315,4 +417,25 @@ mod tests {
         assert_eq!(processor.process_message("AUTOHOME 01").unwrap(), None);
         assert_eq!(processor.process_message("AUTOHOME?").unwrap(), Some("AUTOHOME=01".to_string()));
     }
+
+    #[test]
+    fn test_fault_injection() {
+        let mut processor = CommandProcessor::new(WARMING_TIME, COOLDOWN_TIME);
+        assert_eq!(processor.process_message("PWR ON").unwrap(), None);
+        std::thread::sleep(Duration::from_secs(WARMING_TIME));
+        assert_eq!(processor.process_message("ERR?").unwrap(), Some("ERR=00".to_string()));
+
+        // Inject a fault: ERR? reports it, PWR? turns abnormal and a
+        // power-dependent query is rejected even with the lamp on.
+        assert_eq!(processor.process_message("ERR 41").unwrap(), None);
+        assert_eq!(processor.process_message("ERR?").unwrap(), Some("ERR=41".to_string()));
+        assert_eq!(processor.process_message("PWR?").unwrap(), Some("PWR=04".to_string()));
+        assert_eq!(processor.process_message("LAMP?"), Err(CommandError::InvalidPowerState));
+
+        // Clearing the fault restores normal behaviour.
+        assert_eq!(processor.process_message("ERR 00").unwrap(), None);
+        assert_eq!(processor.process_message("PWR?").unwrap(), Some("PWR=01".to_string()));
+        assert_eq!(processor.process_message("LAMP?").unwrap(), Some(format!("LAMP={LAMP_HOURS_DEFAULT}")));
+        assert_eq!(processor.process_message("ERR 9"), Err(CommandError::InvalidValue));
+    }
 }
\ No newline at end of file
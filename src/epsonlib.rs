@@ -1,8 +1,8 @@
 use std::io::{Read, Write};
 
 use crate::{
-    codec,
-    commands::CommandProcessor
+    commands::CommandProcessor,
+    escvp21::{start_with_processor, SessionConfig},
 };
 
 pub struct Epsonlib<'a, T: 'a + Read + Write> {
@@ -18,47 +18,11 @@ impl<'a, T: 'a + Read + Write> Epsonlib<'a, T> {
         }
     }
 
-    pub fn run_until(&mut self) {
-        let mut serial_buf: Vec<u8> = vec![0; 128];
-        let mut codec = codec::Codec::new();
-
-        let mut processor = CommandProcessor::new();
-        loop {
-            match self.port.read(serial_buf.as_mut_slice()) {
-                Ok(t) => {
-                    if t > 0 {
-                        //println!("Read {} bytes: {:?}", t, &serial_buf[..t]);
-
-                        match codec.decode(&serial_buf[..t]) {
-                            Ok(Some(s)) => {
-                                println!("Decoded: {:?}", s);
-                                match processor.process_message(&s) {
-                                    Ok(Some(output)) => {
-                                        println!("Output: {output}");
-                                        self.port.write(output.as_bytes()).unwrap();
-                                    },
-                                    Ok(None) => (),
-                                    Err(e) => {
-                                        eprintln!("Projector error {:?} for command {s}", e);
-                                        self.port.write(b"ERR").unwrap();
-                                    },
-                                }
-                                self.port.write(b"\r:").unwrap();
-                            }
-                            Ok(None) => (),
-                            Err(e) => eprintln!("Error: {:?}", e),
-                        };
-                    }
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                    eprintln!("timeout");
-                },
-                Err(e) => {
-                    eprintln!("{:?}", e);
-                    break;
-                }
-            }
-        }
+    pub fn run_until(&mut self, warming: u64, cooling: u64, config: SessionConfig) {
+        // The session loop lives in `escvp21`; delegate to it rather than
+        // keeping a second copy in sync.
+        let processor = CommandProcessor::new(warming, cooling);
+        start_with_processor(&mut *self.port, processor, config);
     }
 }
 
@@ -73,7 +37,7 @@ mod tests {
 
         std::thread::spawn(move || {
             let mut epson = Epsonlib::new(&mut slave);
-            epson.run_until();
+            epson.run_until(2, 1, SessionConfig::default());
         });
 
         master.write(b"SNO?\r").unwrap();
@@ -81,14 +45,21 @@ mod tests {
         let mut buf: Vec<u8> = vec![0; 128];
         let t = master.read(buf.as_mut_slice()).unwrap();
         let output = String::from_utf8(buf[..t].to_vec()).unwrap();
-        assert_eq!(output, "1234567890\r:");
+        assert_eq!(output, "SNO=1234567890");
+
+        let t = master.read(buf.as_mut_slice()).unwrap();
+        let output = String::from_utf8(buf[..t].to_vec()).unwrap();
+        assert_eq!(output, "\r:");
 
         // Testing error case
         master.write(b"SNO 1234567890\r").unwrap();
 
         let t = master.read(buf.as_mut_slice()).unwrap();
-        println!("Read {} bytes: {:?}", t, &buf[..t]);
         let output = String::from_utf8(buf[..t].to_vec()).unwrap();
-        assert_eq!(output, "ERR\r:");
+        assert_eq!(output, "ERR");
+
+        let t = master.read(buf.as_mut_slice()).unwrap();
+        let output = String::from_utf8(buf[..t].to_vec()).unwrap();
+        assert_eq!(output, "\r:");
     }
 }
\ No newline at end of file
@@ -1,15 +1,39 @@
 use std::io::{Read, Write, Error, ErrorKind};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::time::{Duration, SystemTime};
 use bytes::BytesMut;
 
 
 use crate::commands::CommandProcessor;
 
+/// Size of an ESC/VP.net framing block (header and password blocks are both
+/// padded to this length).
+const NET_BLOCK_LEN: usize = 16;
+/// Magic prefix that opens every ESC/VP.net header.
+const NET_MAGIC: &[u8; 10] = b"ESC/VP.net";
+/// Protocol version byte following the magic.
+const NET_VERSION: u8 = 0x10;
+/// Header type byte for a CONNECT request.
+const NET_TYPE_CONNECT: u8 = 0x03;
+/// Status byte written back on a successful connect.
+const NET_STATUS_OK: u8 = 0x20;
+/// Status byte signalling that authentication is required.
+const NET_STATUS_AUTH: u8 = 0x41;
+/// Status byte signalling a malformed or rejected connect.
+const NET_STATUS_ERR: u8 = 0x43;
+
 
 pub struct Codec {
     // private
     buffer: BytesMut,
 }
 
+impl Default for Codec {
+    fn default() -> Codec {
+        Codec::new()
+    }
+}
+
 impl Codec {
     pub fn new() -> Codec {
         Codec {
@@ -26,7 +50,7 @@ impl Codec {
             line.resize(line.len() - 1, 0); // Removing the training \r
             let str_result = match std::str::from_utf8(line.as_ref()) {
                 Ok(s) => Ok(Some(s.to_string())),
-                Err(_) => Err(Error::new(ErrorKind::Other, "Invalid String")),
+                Err(_) => Err(Error::other("Invalid String")),
             };
             self.buffer.clear();
             return str_result;
@@ -36,40 +60,107 @@ impl Codec {
 
 }
 
-pub fn start<T: Read + Write>(mut port: T, warming: u32, cooling: u32) {
+/// Tunables for a single emulation session.
+///
+/// `read_timeout`/`write_timeout` are applied by [`start_tcp`] to each
+/// accepted socket; [`start`] and [`start_with_processor`] do not set them on
+/// the port they are given, which keeps its own timeouts. `idle_timeout` bounds how long the loop
+/// tolerates uninterrupted read timeouts before tearing the session down, and
+/// `keepalive`, when set, emits a bare prompt periodically so an idle client
+/// knows the link is still alive.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            read_timeout: Duration::from_secs(60),
+            write_timeout: Duration::from_secs(60),
+            idle_timeout: None,
+            keepalive: None,
+        }
+    }
+}
+
+pub fn start<T: Read + Write>(port: T, warming: u32, cooling: u32, config: SessionConfig) {
+    let processor = CommandProcessor::new(warming as u64, cooling as u64);
+    start_with_processor(port, processor, config);
+}
+
+/// Drives the ASCII ESC/VP21 loop over `port` using a caller-supplied
+/// [`CommandProcessor`], so the command table (e.g. one loaded from an
+/// external model file) and power timers are decided before the loop starts.
+///
+/// Writes propagate their error and end the session instead of panicking,
+/// repeated read timeouts past `config.idle_timeout` close the session
+/// cleanly, and an optional keep-alive prompt is emitted between timeouts.
+pub fn start_with_processor<T: Read + Write>(mut port: T, mut processor: CommandProcessor, config: SessionConfig) {
     let mut serial_buf: Vec<u8> = vec![0; 128];
     let mut codec = Codec::new();
 
-    let mut processor = CommandProcessor::new(warming as u64, cooling as u64);
+    let mut last_activity = SystemTime::now();
+    let mut last_keepalive = SystemTime::now();
+
     loop {
         match port.read(serial_buf.as_mut_slice()) {
+            Ok(0) => {
+                // A clean read of zero bytes means the peer hung up.
+                println!("Session closed by peer");
+                break;
+            }
             Ok(t) => {
-                if t > 0 {
-                    //println!("Read {} bytes: {:?}", t, &serial_buf[..t]);
-
-                    match codec.decode(&serial_buf[..t]) {
-                        Ok(Some(s)) => {
-                            println!("Decoded: {:?}", s);
-                            match processor.process_message(&s) {
-                                Ok(Some(output)) => {
-                                    println!("Output: {output}");
-                                    port.write(output.as_bytes()).unwrap();
-                                },
-                                Ok(None) => (),
-                                Err(e) => {
-                                    eprintln!("Projector error {:?} for command {s}", e);
-                                    port.write(b"ERR").unwrap();
-                                },
-                            }
-                            port.write(b"\r:").unwrap();
+                last_activity = SystemTime::now();
+                // Genuine traffic also defers the keep-alive, so a prompt only
+                // fires after a real lull rather than right after a burst.
+                last_keepalive = last_activity;
+                //println!("Read {} bytes: {:?}", t, &serial_buf[..t]);
+
+                match codec.decode(&serial_buf[..t]) {
+                    Ok(Some(s)) => {
+                        println!("Decoded: {:?}", s);
+                        let write = match processor.process_message(&s) {
+                            Ok(Some(output)) => {
+                                println!("Output: {output}");
+                                port.write_all(output.as_bytes())
+                            },
+                            Ok(None) => Ok(()),
+                            Err(e) => {
+                                eprintln!("Projector error {:?} for command {s}", e);
+                                port.write_all(b"ERR")
+                            },
+                        }.and_then(|()| port.write_all(b"\r:"));
+
+                        if let Err(e) = write {
+                            eprintln!("Write error, ending session: {:?}", e);
+                            break;
                         }
-                        Ok(None) => (),
-                        Err(e) => eprintln!("Error: {:?}", e),
-                    };
-                }
+                    }
+                    Ok(None) => (),
+                    Err(e) => eprintln!("Error: {:?}", e),
+                };
             }
-            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                eprintln!("timeout");
+            Err(ref e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => {
+                if let Some(idle) = config.idle_timeout {
+                    if last_activity.elapsed().unwrap_or_default() > idle {
+                        println!("Idle timeout, ending session");
+                        break;
+                    }
+                }
+
+                if let Some(interval) = config.keepalive {
+                    if last_keepalive.elapsed().unwrap_or_default() > interval {
+                        if let Err(e) = port.write_all(b":") {
+                            eprintln!("Keep-alive write error, ending session: {:?}", e);
+                            break;
+                        }
+                        last_keepalive = SystemTime::now();
+                    }
+                }
             },
             Err(e) => {
                 eprintln!("{:?}", e);
@@ -79,6 +170,89 @@ pub fn start<T: Read + Write>(mut port: T, warming: u32, cooling: u32) {
     }
 }
 
+/// Performs the ESC/VP.net CONNECT handshake on a freshly accepted stream.
+///
+/// Reads the 16-byte client header, validates the `ESC/VP.net` magic and
+/// CONNECT type, and replies with a header whose status field is set to
+/// [`NET_STATUS_OK`]. A no-auth CONNECT sends no password block and proceeds
+/// straight to the ASCII protocol, so the handshake returns as soon as the OK
+/// reply is written — reading a speculative password block here would swallow
+/// the client's first command. A header without the `ESC/VP.net` magic is
+/// answered with [`NET_STATUS_ERR`], and one of any type other than CONNECT
+/// with [`NET_STATUS_AUTH`]; in both cases the handshake fails.
+fn net_handshake<T: Read + Write>(port: &mut T) -> Result<(), Error> {
+    let mut header = [0u8; NET_BLOCK_LEN];
+    port.read_exact(&mut header)?;
+
+    if &header[..NET_MAGIC.len()] != NET_MAGIC {
+        let mut reply = [0u8; NET_BLOCK_LEN];
+        reply[..NET_MAGIC.len()].copy_from_slice(NET_MAGIC);
+        reply[10] = NET_VERSION;
+        reply[NET_BLOCK_LEN - 1] = NET_STATUS_ERR;
+        port.write_all(&reply)?;
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid ESC/VP.net header"));
+    }
+
+    let mut reply = header;
+    reply[10] = NET_VERSION;
+    // Authentication is not modelled, so a CONNECT always succeeds.
+    let status = if header[11] == NET_TYPE_CONNECT {
+        NET_STATUS_OK
+    } else {
+        NET_STATUS_AUTH
+    };
+    reply[NET_BLOCK_LEN - 1] = status;
+    port.write_all(&reply)?;
+
+    if status != NET_STATUS_OK {
+        return Err(Error::new(ErrorKind::PermissionDenied, "ESC/VP.net connect rejected"));
+    }
+
+    // Authentication is not modelled, so no password block is expected: the
+    // next bytes on the socket are the first ASCII command and belong to the
+    // session loop, not to the handshake.
+    Ok(())
+}
+
+/// Listens on `addr` for ESC/VP.net (TCP port 3629) clients and drives each
+/// one through the ordinary ESC/VP21 loop.
+///
+/// After the [`net_handshake`] completes the socket carries exactly the
+/// serial ASCII protocol, so every accepted connection is handed to [`start`]
+/// with its own [`CommandProcessor`], giving each client independent power
+/// state. Each connection is served on its own thread so a slow or silent
+/// client cannot wedge the listener or block other clients from connecting.
+pub fn start_tcp<A: ToSocketAddrs>(addr: A, warming: u32, cooling: u32, config: SessionConfig) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Accept error: {:?}", e);
+                continue;
+            }
+        };
+
+        let config = config.clone();
+        std::thread::spawn(move || {
+            // The socket honours the same read/write timeouts the loop reasons
+            // about, so idle handling and keep-alive actually fire. The read
+            // timeout is set before the handshake so a client that connects but
+            // never sends the header cannot park this thread forever.
+            let _ = stream.set_read_timeout(Some(config.read_timeout));
+            let _ = stream.set_write_timeout(Some(config.write_timeout));
+
+            if let Err(e) = net_handshake(&mut stream) {
+                eprintln!("Handshake failed: {:?}", e);
+                return;
+            }
+
+            start(stream, warming, cooling, config);
+        });
+    }
+    Ok(())
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -126,7 +300,7 @@ mod tests {
         let (mut master, mut slave) = VirtualPort::pair(); //.unwrap();
 
         std::thread::spawn(move || {
-            start(&mut slave,2, 1);
+            start(&mut slave, 2, 1, SessionConfig::default());
         });
 
         master.write(b"SNO?\r").unwrap();
@@ -0,0 +1,157 @@
+use std::io;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::commands::CommandProcessor;
+
+/// A single step of a transcript fixture.
+///
+/// Every field is optional so one JSON object can express any of the step
+/// kinds: a bare `send`, a `send`/`expect` request/response pair, a
+/// `sleep_ms` delay (to cross a warming/cooling boundary), or an
+/// `expect_error` assertion naming a [`crate::commands::CommandError`]
+/// variant (e.g. `"InvalidPowerState"`).
+#[derive(Debug, Deserialize)]
+pub struct Step {
+    #[serde(default)]
+    pub send: Option<String>,
+    #[serde(default)]
+    pub expect: Option<String>,
+    #[serde(default)]
+    pub sleep_ms: Option<u64>,
+    #[serde(default)]
+    pub expect_error: Option<String>,
+}
+
+/// Assertion tally for one fixture file.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixtureSummary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// Runs every `*.json` fixture found directly under `dir` against a fresh
+/// [`CommandProcessor`] configured with `warming`/`cooling`, printing a
+/// per-file pass/fail summary and returning the aggregate.
+///
+/// Each file is a JSON array of [`Step`]s applied in order; a mismatched
+/// response or error counts as a failed assertion but does not abort the
+/// run, so one fixture can capture a whole projector session.
+pub fn run_fixtures(dir: &Path, warming: u64, cooling: u64) -> io::Result<FixtureSummary> {
+    let mut total = FixtureSummary::default();
+
+    let mut entries = std::fs::read_dir(dir)?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|e| e.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let summary = run_fixture_file(&path, warming, cooling)?;
+        println!(
+            "{}: {} passed, {} failed",
+            path.display(),
+            summary.passed,
+            summary.failed
+        );
+        total.passed += summary.passed;
+        total.failed += summary.failed;
+    }
+
+    println!("Total: {} passed, {} failed", total.passed, total.failed);
+    Ok(total)
+}
+
+/// Applies the steps of a single fixture file and returns its tally.
+pub fn run_fixture_file(path: &Path, warming: u64, cooling: u64) -> io::Result<FixtureSummary> {
+    let contents = std::fs::read_to_string(path)?;
+    let steps: Vec<Step> = serde_json::from_str(&contents)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    let mut processor = CommandProcessor::new(warming, cooling);
+    let mut summary = FixtureSummary::default();
+
+    for step in steps {
+        if let Some(ms) = step.sleep_ms {
+            sleep(Duration::from_millis(ms));
+        }
+
+        let Some(message) = step.send.as_deref() else {
+            continue;
+        };
+
+        match processor.process_message(message) {
+            Ok(response) => {
+                if let Some(expected) = &step.expect_error {
+                    summary.failed += 1;
+                    eprintln!("{message}: expected error {expected}, got {response:?}");
+                } else if let Some(expected) = &step.expect {
+                    if response.as_deref() == Some(expected.as_str()) {
+                        summary.passed += 1;
+                    } else {
+                        summary.failed += 1;
+                        eprintln!("{message}: expected {expected:?}, got {response:?}");
+                    }
+                } else {
+                    summary.passed += 1;
+                }
+            }
+            Err(err) => {
+                if let Some(expected) = &step.expect_error {
+                    if format!("{err:?}") == *expected {
+                        summary.passed += 1;
+                    } else {
+                        summary.failed += 1;
+                        eprintln!("{message}: expected error {expected}, got {err:?}");
+                    }
+                } else {
+                    summary.failed += 1;
+                    eprintln!("{message}: unexpected error {err:?}");
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WARMING_TIME: u64 = 2;
+    const COOLDOWN_TIME: u64 = 1;
+
+    /// A transcript exercising a `send`-only step, a `send`/`expect` pair, a
+    /// `sleep_ms` boundary that crosses the warming timer, and an
+    /// `expect_error` assertion.
+    const TRANSCRIPT: &str = r#"[
+        { "send": "PWR ON" },
+        { "send": "PWR?", "expect": "PWR=02" },
+        { "sleep_ms": 2100, "send": "PWR?", "expect": "PWR=01" },
+        { "send": "LAMP?", "expect": "LAMP=100" },
+        { "send": "SNO 123", "expect_error": "InvalidCommand" }
+    ]"#;
+
+    #[test]
+    fn test_run_fixtures() {
+        let dir = std::env::temp_dir().join(format!("escvp21_fixtures_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("session.json"), TRANSCRIPT).unwrap();
+        // A non-JSON file is ignored by the directory scan.
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let summary = run_fixtures(&dir, WARMING_TIME, COOLDOWN_TIME).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(summary.passed, 5);
+        assert_eq!(summary.failed, 0);
+    }
+}
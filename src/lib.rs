@@ -0,0 +1,7 @@
+pub mod client;
+pub mod codec;
+pub mod commands;
+pub mod epsonlib;
+pub mod fixtures;
+pub mod escvp21;
+pub mod model;
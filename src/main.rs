@@ -1,8 +1,11 @@
+use std::path::PathBuf;
 use std::time::Duration;
 use clap::{
     Parser,
     Subcommand
 };
+use escvp21emulator::commands::CommandProcessor;
+use escvp21emulator::escvp21::SessionConfig;
 use serialport::{
     SerialPortType::UsbPort,
     available_ports,
@@ -30,6 +33,20 @@ enum Commands {
         warming: u32,
         #[arg(short, long, default_value_t=5)]
         cooling: u32,
+        /// Optional projector model definition (TOML/JSON); built-in table when omitted
+        #[arg(short, long)]
+        model: Option<PathBuf>,
+    },
+    /// Listen for ESC/VP.net clients over TCP (projector port 3629)
+    Listen {
+        #[arg(short, long, default_value="0.0.0.0")]
+        bind: String,
+        #[arg(short='P', long, default_value_t=3629)]
+        port: u16,
+        #[arg(short, long, default_value_t=20)]
+        warming: u32,
+        #[arg(short, long, default_value_t=5)]
+        cooling: u32,
     }
 }
 
@@ -59,12 +76,21 @@ fn main() -> std::io::Result<()> {
 
                 println!("Available ports:\n{}", ports.join("\n"));
             },
-            Commands::Open { port, baud_rate,warming, cooling } => {
+            Commands::Open { port, baud_rate, warming, cooling, model } => {
                 let port = serialport::new(port, baud_rate)
                     .timeout(Duration::from_secs(60))
                     .open()?;
 
-                escvp21emulator::escvp21::start(port, cooling, warming);
+                let processor = match model {
+                    Some(path) => CommandProcessor::from_model(&path, warming as u64, cooling as u64)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?,
+                    None => CommandProcessor::new(warming as u64, cooling as u64),
+                };
+
+                escvp21emulator::escvp21::start_with_processor(port, processor, SessionConfig::default());
+            },
+            Commands::Listen { bind, port, warming, cooling } => {
+                escvp21emulator::escvp21::start_tcp((bind.as_str(), port), warming, cooling, SessionConfig::default())?;
             }
         }
     }
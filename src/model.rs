@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::commands::{ModelError, Param};
+
+/// A single command entry as described in an external model file.
+#[derive(Debug, Deserialize)]
+pub struct CommandDef {
+    /// Value returned by `CMD?` before anything is set.
+    pub default: String,
+    /// Regex the set path must match; empty means the command is read-only.
+    #[serde(default)]
+    pub validation: String,
+    /// Whether `CMD?`/`CMD <v>` is answered while the lamp is off.
+    #[serde(default)]
+    pub supported_in_power_off: bool,
+}
+
+/// Parses a projector model definition into the same [`Param`] table the
+/// built-in emulator uses.
+///
+/// The format is chosen from the file extension: `.json` is read as JSON,
+/// everything else as TOML. Each entry is validated at load time, so a bad
+/// regex is reported as [`ModelError::Regex`] rather than panicking inside
+/// [`Param`].
+pub fn load(path: &Path) -> Result<HashMap<String, Param>, ModelError> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let defs: HashMap<String, CommandDef> = if path.extension().and_then(|e| e.to_str()) == Some("json") {
+        serde_json::from_str(&contents).map_err(|e| ModelError::Parse(e.to_string()))?
+    } else {
+        toml::from_str(&contents).map_err(|e| ModelError::Parse(e.to_string()))?
+    };
+
+    let mut commands = HashMap::with_capacity(defs.len());
+    for (name, def) in defs {
+        let param = Param::try_new(&name, &def.default, &def.validation, def.supported_in_power_off)?;
+        commands.insert(name, param);
+    }
+    Ok(commands)
+}